@@ -1,8 +1,12 @@
 use burn::{
-    module::Module,
-    nn::{loss::CrossEntropyLossConfig, Dropout, DropoutConfig, Linear, LinearConfig, Relu},
+    config::Config,
+    module::{Module, Param},
+    nn::{
+        loss::CrossEntropyLossConfig, Dropout, DropoutConfig, Gelu, Initializer, LayerNorm,
+        LayerNormConfig, Linear, LinearConfig, Relu,
+    },
     prelude::Backend,
-    tensor::{backend::AutodiffBackend, DataError, Tensor, TensorData},
+    tensor::{activation::softmax, backend::AutodiffBackend, Tensor},
     train::{ClassificationOutput, TrainOutput, TrainStep, ValidStep},
 };
 
@@ -12,76 +16,245 @@ const INPUT_SIZE: usize = 23;
 const HIDDEN_SIZES: [usize; 6] = [64, 128, 256, 512, 1024, 2048];
 const OUTPUT_SIZE: usize = 2;
 
-#[derive(Module, Debug)]
-pub struct Model<B: Backend> {
-    input_layer: Linear<B>,
-    ln1: Linear<B>,
-    ln2: Linear<B>,
-    ln3: Linear<B>,
-    output_layer: Linear<B>,
-    dropout: Dropout,
-    activation: Relu,
+/// Sequential MLP configuration, in the style of Keras' `Sequential`: callers pick the input
+/// width, an arbitrary stack of hidden widths, and the output width, rather than editing
+/// hardcoded constants and named `Linear` fields every time the architecture changes.
+#[derive(Config, Debug)]
+pub struct ModelConfig {
+    pub input_size: usize,
+    pub hidden_sizes: Vec<usize>,
+    pub output_size: usize,
+    #[config(default = 0.5)]
+    pub dropout: f64,
+    /// Activation applied after every hidden layer's linear transform.
+    #[config(default = "ActivationConfig::Relu")]
+    pub activation: ActivationConfig,
+    /// Weight initializer applied to every `Linear` layer's kernel.
+    #[config(default = "Initializer::KaimingUniform { gain: 1.0, fan_out_only: false }")]
+    pub initializer: Initializer,
+    /// Bias initializer, configured separately from `initializer`.
+    #[config(default = "Initializer::Constant { value: 0.0 }")]
+    pub bias_initializer: Initializer,
+    /// When set, mix every hidden layer's activations with learned scalar weights (ELMo-style)
+    /// instead of feeding only the last hidden layer into `output_layer`. Requires every hidden
+    /// layer to share one width.
+    #[config(default = false)]
+    pub aggregate_layers: bool,
 }
 
-impl<B: Backend> Default for Model<B> {
-    fn default() -> Self {
-        let device = B::Device::default();
-        Self::new(&device)
+impl ModelConfig {
+    pub fn init<B: Backend>(&self, device: &B::Device) -> Model<B> {
+        let mut layers = Vec::with_capacity(self.hidden_sizes.len());
+        let mut prev_size = self.input_size;
+
+        for &hidden_size in &self.hidden_sizes {
+            layers.push(NonLinearWithLayerNorm::new(
+                prev_size,
+                hidden_size,
+                self.dropout,
+                self.activation.init(),
+                self.initializer.clone(),
+                self.bias_initializer.clone(),
+                device,
+            ));
+            prev_size = hidden_size;
+        }
+
+        let output_layer = linear_with_initializers(
+            prev_size,
+            self.output_size,
+            self.initializer.clone(),
+            self.bias_initializer.clone(),
+            device,
+        );
+
+        let (layer_weights, gamma) = if self.aggregate_layers {
+            assert!(
+                !self.hidden_sizes.is_empty()
+                    && self.hidden_sizes.windows(2).all(|w| w[0] == w[1]),
+                "aggregate_layers requires at least one hidden layer, with every hidden layer \
+                 sharing one width"
+            );
+
+            let num_layers = self.hidden_sizes.len();
+            let weights = Tensor::zeros([num_layers], device);
+            let gamma = Tensor::ones([1], device);
+
+            (
+                Some(Param::from_tensor(weights)),
+                Some(Param::from_tensor(gamma)),
+            )
+        } else {
+            (None, None)
+        };
+
+        Model {
+            layers,
+            output_layer,
+            layer_weights,
+            gamma,
+        }
     }
 }
 
-impl<B: Backend> Model<B> {
-    pub fn new(device: &B::Device) -> Self {
-        let h1 = HIDDEN_SIZES[0];
-        let h2 = HIDDEN_SIZES[1];
-        let h3 = HIDDEN_SIZES[2];
-        let input_layer = LinearConfig::new(INPUT_SIZE, h1)
-            .with_bias(true)
-            .init(device);
+/// Quiet softmax: `exp(x_i - m) / (exp(-m) + sum_j exp(x_j - m))` with `m` the row max, the
+/// numerically stable form of `exp(x_i) / (1 + sum_j exp(x_j))`.
+fn quiet_softmax<B: Backend>(logits: Tensor<B, 2>) -> Tensor<B, 2> {
+    let max = logits.clone().max_dim(1);
+    let shifted = logits.sub(max.clone());
+    let numerator = shifted.exp();
+    let denominator = max.neg().exp() + numerator.clone().sum_dim(1);
+
+    numerator.div(denominator)
+}
+
+/// ELMo-style mix of every hidden layer's output: `gamma * sum_l softmax(layer_weights)[l] * h_l`.
+fn aggregate_hidden_layers<B: Backend>(
+    hidden_outputs: Vec<Tensor<B, 2>>,
+    layer_weights: &Param<Tensor<B, 1>>,
+    gamma: &Param<Tensor<B, 1>>,
+) -> Tensor<B, 2> {
+    let num_layers = hidden_outputs.len();
+    let [batch_size, hidden_size] = hidden_outputs[0].dims();
+
+    let scores = softmax(layer_weights.val(), 0).reshape([num_layers, 1, 1]);
+    let stacked = Tensor::stack::<3>(hidden_outputs, 0);
+    let mixed = (stacked * scores)
+        .sum_dim(0)
+        .reshape([batch_size, hidden_size]);
+
+    mixed * gamma.val().reshape([1, 1])
+}
 
-        let ln1 = LinearConfig::new(h1, h2).with_bias(true).init(device);
+/// Builds a `Linear` with independently configured weight and bias initializers.
+fn linear_with_initializers<B: Backend>(
+    input_size: usize,
+    output_size: usize,
+    initializer: Initializer,
+    bias_initializer: Initializer,
+    device: &B::Device,
+) -> Linear<B> {
+    let linear = LinearConfig::new(input_size, output_size)
+        .with_bias(true)
+        .with_initializer(initializer)
+        .init(device);
 
-        let ln2 = LinearConfig::new(h2, h2).with_bias(true).init(device);
+    Linear {
+        bias: Some(bias_initializer.init([output_size], device)),
+        ..linear
+    }
+}
+
+/// Activation choice for `ModelConfig`.
+#[derive(Config, Debug)]
+pub enum ActivationConfig {
+    Relu,
+    Gelu,
+}
 
-        let ln3 = LinearConfig::new(h2, h3).with_bias(true).init(device);
+impl ActivationConfig {
+    fn init(&self) -> Activation {
+        match self {
+            ActivationConfig::Relu => Activation::Relu(Relu::new()),
+            ActivationConfig::Gelu => Activation::Gelu(Gelu::new()),
+        }
+    }
+}
 
-        let output_layer = LinearConfig::new(h3, OUTPUT_SIZE)
-            .with_bias(true)
-            .init(device);
+#[derive(Module, Debug)]
+pub enum Activation {
+    Relu(Relu),
+    Gelu(Gelu),
+}
 
-        let dropout = DropoutConfig::new(0.5).init();
-        let activation = Relu::new();
+impl Activation {
+    fn forward<B: Backend>(&self, input: Tensor<B, 2>) -> Tensor<B, 2> {
+        match self {
+            Activation::Relu(activation) => activation.forward(input),
+            Activation::Gelu(activation) => activation.forward(input),
+        }
+    }
+}
+
+/// One hidden layer of the MLP: `linear -> activation -> LayerNorm -> dropout`.
+#[derive(Module, Debug)]
+pub struct NonLinearWithLayerNorm<B: Backend> {
+    linear: Linear<B>,
+    activation: Activation,
+    layer_norm: LayerNorm<B>,
+    dropout: Dropout,
+}
+
+impl<B: Backend> NonLinearWithLayerNorm<B> {
+    pub fn new(
+        input_size: usize,
+        output_size: usize,
+        dropout: f64,
+        activation: Activation,
+        initializer: Initializer,
+        bias_initializer: Initializer,
+        device: &B::Device,
+    ) -> Self {
+        let linear = linear_with_initializers(
+            input_size,
+            output_size,
+            initializer,
+            bias_initializer,
+            device,
+        );
+        let layer_norm = LayerNormConfig::new(output_size).init(device);
+        let dropout = DropoutConfig::new(dropout).init();
 
         Self {
-            input_layer,
-            ln1,
-            ln2,
-            ln3,
-            output_layer,
-            dropout,
+            linear,
             activation,
+            layer_norm,
+            dropout,
         }
     }
 
     pub fn forward(&self, input: Tensor<B, 2>) -> Tensor<B, 2> {
-        let x = input.detach();
-        let x = self.input_layer.forward(x);
+        let x = self.linear.forward(input);
         let x = self.activation.forward(x);
-        let x = self.dropout.forward(x);
+        let x = self.layer_norm.forward(x);
+        self.dropout.forward(x)
+    }
+}
 
-        let x = self.ln1.forward(x);
-        let x = self.activation.forward(x);
-        let x = self.dropout.forward(x);
+#[derive(Module, Debug)]
+pub struct Model<B: Backend> {
+    layers: Vec<NonLinearWithLayerNorm<B>>,
+    output_layer: Linear<B>,
+    /// `Some` only when `aggregate_layers` was set.
+    layer_weights: Option<Param<Tensor<B, 1>>>,
+    gamma: Option<Param<Tensor<B, 1>>>,
+}
 
-        let x = self.ln2.forward(x);
-        let x = self.activation.forward(x);
-        let x = self.dropout.forward(x);
+impl<B: Backend> Default for Model<B> {
+    fn default() -> Self {
+        let device = B::Device::default();
+        ModelConfig::new(INPUT_SIZE, HIDDEN_SIZES.to_vec(), OUTPUT_SIZE).init(&device)
+    }
+}
 
-        let x = self.ln3.forward(x);
-        let x = self.activation.forward(x);
-        let x = self.dropout.forward(x);
+impl<B: Backend> Model<B> {
+    pub fn forward(&self, input: Tensor<B, 2>) -> Tensor<B, 2> {
+        let mut x = input.detach();
+        let mut hidden_outputs = Vec::with_capacity(self.layers.len());
+
+        for layer in &self.layers {
+            x = layer.forward(x);
+            hidden_outputs.push(x.clone());
+        }
 
-        self.output_layer.forward(x)
+        let aggregated = match (&self.layer_weights, &self.gamma) {
+            (Some(layer_weights), Some(gamma)) => {
+                aggregate_hidden_layers(hidden_outputs, layer_weights, gamma)
+            }
+            _ => x,
+        };
+
+        self.output_layer.forward(aggregated)
     }
 
     pub fn forward_step(&self, item: DailyLinearBatch<B>) -> ClassificationOutput<B> {
@@ -100,10 +273,33 @@ impl<B: Backend> Model<B> {
         }
     }
 
+    /// Same as `forward_step`, but trains against quiet-softmax's log-likelihood of the target
+    /// class instead of standard cross-entropy.
+    pub fn forward_step_quiet_softmax(&self, item: DailyLinearBatch<B>) -> ClassificationOutput<B> {
+        let targets = item.targets;
+        let output = self.forward(item.inputs);
+
+        let target_probs = quiet_softmax(output.clone())
+            .gather(1, targets.clone().unsqueeze_dim::<2>(1))
+            .squeeze(1);
+        let loss = target_probs.clamp_min(1e-12).log().neg().mean();
+
+        ClassificationOutput {
+            loss,
+            output,
+            targets,
+        }
+    }
+
     pub fn infer(&self, item: DailyLinearInferBatch<B>) -> Tensor<B, 2> {
         let output = self.forward(item.inputs);
         output
     }
+
+    /// Quiet-softmax probabilities: may sum to less than one, signaling abstention.
+    pub fn infer_quiet_softmax(&self, item: DailyLinearInferBatch<B>) -> Tensor<B, 2> {
+        quiet_softmax(self.forward(item.inputs))
+    }
 }
 
 impl<B: AutodiffBackend> TrainStep<DailyLinearBatch<B>, ClassificationOutput<B>> for Model<B> {
@@ -119,3 +315,54 @@ impl<B: Backend> ValidStep<DailyLinearBatch<B>, ClassificationOutput<B>> for Mod
         self.forward_step(item)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use burn_ndarray::NdArray;
+
+    type TestBackend = NdArray<f32>;
+
+    #[test]
+    #[should_panic(expected = "aggregate_layers requires at least one hidden layer")]
+    fn aggregate_layers_rejects_empty_hidden_sizes() {
+        let device = <TestBackend as Backend>::Device::default();
+
+        let _model: Model<TestBackend> = ModelConfig::new(4, vec![], 2)
+            .with_aggregate_layers(true)
+            .init(&device);
+    }
+
+    #[test]
+    fn aggregate_hidden_layers_averages_uniformly_at_init() {
+        let device = <TestBackend as Backend>::Device::default();
+        let a: Tensor<TestBackend, 2> = Tensor::from_floats([[1.0, 2.0]], &device);
+        let b: Tensor<TestBackend, 2> = Tensor::from_floats([[3.0, 4.0]], &device);
+
+        // Fresh ELMo params: zero weights -> uniform softmax, gamma one -> plain average.
+        let weights = Param::from_tensor(Tensor::zeros([2], &device));
+        let gamma = Param::from_tensor(Tensor::ones([1], &device));
+
+        let mixed = aggregate_hidden_layers(vec![a, b], &weights, &gamma);
+        let mixed_data: Vec<f32> = mixed.to_data().to_vec().unwrap();
+
+        assert_eq!(mixed_data, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn quiet_softmax_matches_naive_formula_and_sums_below_one() {
+        let device = <TestBackend as Backend>::Device::default();
+        let raw = [0.5_f32, -1.0, 2.0];
+        let logits: Tensor<TestBackend, 2> = Tensor::from_floats([raw], &device);
+
+        let probs: Vec<f32> = quiet_softmax(logits).to_data().to_vec().unwrap();
+
+        let denom = 1.0 + raw.iter().map(|v| v.exp()).sum::<f32>();
+        let naive: Vec<f32> = raw.iter().map(|v| v.exp() / denom).collect();
+
+        for (stable, naive) in probs.iter().zip(naive.iter()) {
+            assert!((stable - naive).abs() < 1e-5, "{stable} vs {naive}");
+        }
+        assert!(probs.iter().sum::<f32>() < 1.0);
+    }
+}